@@ -0,0 +1,164 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression as GzLevel;
+use tar::Builder;
+use xz2::stream::{LzmaOptions, Stream};
+use xz2::write::XzEncoder;
+
+use crate::drivers::Driver;
+use crate::errors::{Result, XcpError};
+use crate::operations::{StatSender, StatusUpdate};
+use crate::options::{ArchiveFormat, Opts};
+use crate::utils::target_path;
+
+/// The compressing writer underneath the tar stream. Kept as a named
+/// enum rather than a `Box<dyn Write>` so that `pack` can call each
+/// algorithm's real `finish()` (which flushes and writes any trailing
+/// frame/footer) and see its result, instead of only ever flushing
+/// the outermost buffer and relying on `Drop` to finalize the stream
+/// and silently swallow any error doing so.
+enum Encoder {
+    Gz(GzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+    Xz(XzEncoder<File>),
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gz(e) => e.write(buf),
+            Encoder::Zstd(e) => e.write(buf),
+            Encoder::Xz(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gz(e) => e.flush(),
+            Encoder::Zstd(e) => e.flush(),
+            Encoder::Xz(e) => e.flush(),
+        }
+    }
+}
+
+impl Encoder {
+    /// Finalize the compression stream, propagating any error (e.g.
+    /// `ENOSPC` while writing the final frame) instead of letting
+    /// `Drop` discard it and leave a truncated archive behind.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Encoder::Gz(e) => e.finish().map(|_| ()),
+            Encoder::Zstd(e) => e.finish().map(|_| ()),
+            Encoder::Xz(e) => e.finish().map(|_| ()),
+        }
+    }
+}
+
+/// Packs sources into a single compressed tar archive at `dest`
+/// instead of reproducing a directory tree, per `--archive`.
+pub struct ArchiveDriver {
+    format: ArchiveFormat,
+    level: u32,
+    xz_dict_size: Option<u32>,
+    recursive: bool,
+}
+
+impl ArchiveDriver {
+    pub fn new(opts: &Opts, format: ArchiveFormat) -> Self {
+        Self {
+            format,
+            level: opts.archive_level,
+            xz_dict_size: opts.archive_xz_dict_size,
+            recursive: opts.recursive,
+        }
+    }
+
+    fn encoder(&self, dest: &Path) -> Result<Encoder> {
+        let file = File::create(dest)?;
+        Ok(match self.format {
+            ArchiveFormat::TarGz => Encoder::Gz(GzEncoder::new(file, GzLevel::new(self.level))),
+            ArchiveFormat::TarZst => Encoder::Zstd(zstd::Encoder::new(file, self.level as i32)?),
+            ArchiveFormat::TarXz => {
+                let mut xz_opts = LzmaOptions::new_preset(self.level)?;
+                if let Some(mib) = self.xz_dict_size {
+                    xz_opts.dict_size(mib * 1024 * 1024);
+                }
+                let stream = Stream::new_easy_encoder(&xz_opts, xz2::stream::Check::Crc64)?;
+                Encoder::Xz(XzEncoder::new_stream(file, stream))
+            }
+        })
+    }
+
+    fn pack(&self, sources: Vec<PathBuf>, dest: &Path, stats: &StatSender) -> Result<()> {
+        let mut builder = Builder::new(self.encoder(dest)?);
+
+        for source in sources {
+            if source.is_dir() && !self.recursive {
+                return Err(XcpError::InvalidSource(
+                    "Source is directory and --recursive not specified.",
+                )
+                .into());
+            }
+
+            let base = source.parent().unwrap_or(&source).to_path_buf();
+            self.add(&mut builder, &source, &base, stats)?;
+        }
+
+        builder.into_inner()?.finish()?;
+        Ok(())
+    }
+
+    fn add(
+        &self,
+        builder: &mut Builder<Encoder>,
+        path: &Path,
+        base: &Path,
+        stats: &StatSender,
+    ) -> Result<()> {
+        let name = target_path(path, base, Path::new(""))?;
+
+        if path.is_dir() {
+            builder.append_dir(&name, path)?;
+            for entry in std::fs::read_dir(path)? {
+                self.add(builder, &entry?.path(), base, stats)?;
+            }
+        } else {
+            let size = path.metadata()?.len();
+            let mut file = File::open(path)?;
+            builder.append_file(&name, &mut file)?;
+            stats.send(StatusUpdate::Copied(size))?;
+            stats.send(StatusUpdate::Size(size))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Driver for ArchiveDriver {
+    fn copy_single(&self, source: &Path, dest: &Path, stats: StatSender) -> Result<()> {
+        self.pack(vec![source.to_path_buf()], dest, &stats)
+    }
+
+    fn copy_all(&self, sources: Vec<PathBuf>, dest: &Path, stats: StatSender) -> Result<()> {
+        self.pack(sources, dest, &stats)
+    }
+}
@@ -0,0 +1,100 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::Path;
+
+use filetime::{set_file_times, FileTime};
+use log::warn;
+
+use crate::errors::Result;
+use crate::operations::{StatSender, StatusUpdate};
+use crate::options::Preserve;
+
+/// Restore whichever of `preserve`'s attributes are requested on
+/// `dest`, reading them from `source`'s metadata. Individual
+/// attributes that fail to restore are reported as non-fatal
+/// `StatusUpdate::Warning`s rather than aborting the copy; a `chown`
+/// denied for lack of privilege is the expected case for non-root
+/// copies and is skipped without even that.
+pub fn apply(source: &Path, dest: &Path, preserve: Preserve, stats: &StatSender) -> Result<()> {
+    let meta = fs::symlink_metadata(source)?;
+
+    if preserve.mode {
+        report(
+            fs::set_permissions(dest, fs::Permissions::from_mode(meta.mode())),
+            dest,
+            stats,
+        )?;
+    }
+
+    if preserve.ownership {
+        report(chown(dest, meta.uid(), meta.gid()), dest, stats)?;
+    }
+
+    if preserve.xattr {
+        report(copy_xattrs(source, dest), dest, stats)?;
+    }
+
+    // Timestamps are restored last (by the caller, for directories,
+    // after all their children have been written) so a later write
+    // into the tree doesn't bump mtime back to "now".
+    if preserve.timestamps {
+        let atime = FileTime::from_last_access_time(&meta);
+        let mtime = FileTime::from_last_modification_time(&meta);
+        report(set_file_times(dest, atime, mtime), dest, stats)?;
+    }
+
+    Ok(())
+}
+
+fn report(result: std::io::Result<()>, dest: &Path, stats: &StatSender) -> Result<()> {
+    if let Err(e) = result {
+        warn!("Failed to restore attribute on {:?}: {}", dest, e);
+        stats.send(StatusUpdate::Warning(dest.to_path_buf(), e.into()))?;
+    }
+    Ok(())
+}
+
+fn chown(dest: &Path, uid: u32, gid: u32) -> std::io::Result<()> {
+    use std::ffi::CString;
+
+    let path = CString::new(dest.as_os_str().to_string_lossy().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    let ret = unsafe { libc::chown(path.as_ptr(), uid, gid) };
+    if ret != 0 {
+        let err = std::io::Error::last_os_error();
+        // Not having permission to chown is the expected case for
+        // non-root copies; skip it silently rather than reporting it
+        // as a failure.
+        if err.raw_os_error() == Some(libc::EPERM) {
+            return Ok(());
+        }
+        return Err(err);
+    }
+    Ok(())
+}
+
+fn copy_xattrs(source: &Path, dest: &Path) -> std::io::Result<()> {
+    for name in xattr::list(source)? {
+        if let Some(value) = xattr::get(source, &name)? {
+            xattr::set(dest, &name, &value)?;
+        }
+    }
+    Ok(())
+}
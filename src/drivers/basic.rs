@@ -0,0 +1,186 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::drivers::{attrs, reflink, sparse, symlinks, Driver};
+use crate::errors::{Result, XcpError};
+use crate::operations::{StatSender, StatusUpdate};
+use crate::options::{Opts, SymlinkPolicy};
+use crate::utils::{backup_path, target_path};
+
+/// The default driver: a straightforward, single-threaded
+/// read/copy-per-file implementation. Each file is reflinked,
+/// sparse-copied or densely copied as `--reflink`/`--sparse` allow;
+/// `load_driver` selects the archive driver instead of this one when
+/// `--archive` is given.
+pub struct BasicDriver {
+    opts: Opts,
+}
+
+impl BasicDriver {
+    pub fn new(opts: &Opts) -> Self {
+        Self { opts: opts.clone() }
+    }
+
+    /// With `--continue`, turn a per-file failure into a reported
+    /// `StatusUpdate::Error` and keep going instead of aborting the
+    /// whole copy.
+    fn guard(&self, path: &Path, stats: &StatSender, result: Result<()>) -> Result<()> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) if self.opts.continue_on_error => {
+                stats.send(StatusUpdate::Error(path.to_path_buf(), XcpError::Other(e.to_string())))?;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Rename an existing destination out of the way, if the
+    /// configured backup mode calls for it.
+    fn make_backup(&self, dest: &Path) -> Result<()> {
+        if let Some(backup) = backup_path(dest, self.opts.backup_mode()?, &self.opts.suffix)? {
+            fs::rename(dest, backup)?;
+        }
+        Ok(())
+    }
+
+    fn copy_file(&self, source: &Path, dest: &Path, stats: &StatSender) -> Result<()> {
+        self.make_backup(dest)?;
+
+        if reflink::try_reflink(source, dest, self.opts.reflink)? {
+            let size = fs::metadata(source)?.len();
+            stats.send(StatusUpdate::Copied(size))?;
+            stats.send(StatusUpdate::Size(size))?;
+        } else if sparse::try_sparse_copy(source, dest, self.opts.sparse, stats)? {
+            // Byte counts already reported by try_sparse_copy.
+        } else {
+            let written = fs::copy(source, dest)?;
+            stats.send(StatusUpdate::Copied(written))?;
+            stats.send(StatusUpdate::Size(written))?;
+        }
+
+        attrs::apply(source, dest, self.opts.preserve(), stats)?;
+        Ok(())
+    }
+
+    /// Recursively walk `source`, copying files and recreating
+    /// directories under `target_path(source, base, dest_base)`.
+    /// `is_root` marks the top-level source argument, which is the
+    /// only place `-H` (command-line-only dereference) follows a
+    /// symlink.
+    #[allow(clippy::too_many_arguments)]
+    fn walk(
+        &self,
+        source: &Path,
+        base: &Path,
+        dest_base: &Path,
+        is_root: bool,
+        visited: &mut symlinks::VisitedDirs,
+        stats: &StatSender,
+        dirs: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Result<()> {
+        let policy = self.opts.symlink_policy();
+        let follow = match policy {
+            SymlinkPolicy::Dereference => true,
+            SymlinkPolicy::CommandLineOnly => is_root,
+            SymlinkPolicy::NoDereference => false,
+        };
+
+        let target = target_path(source, base, dest_base)?;
+        let meta = if follow {
+            fs::metadata(source)
+        } else {
+            fs::symlink_metadata(source)
+        }?;
+
+        if meta.file_type().is_symlink() {
+            symlinks::recreate(source, &target)?;
+            return Ok(());
+        }
+
+        if meta.is_dir() {
+            fs::create_dir_all(&target)?;
+            if follow && !visited.visit(source)? {
+                // Already descended into this directory via another
+                // symlink; stop here to avoid an infinite loop.
+                return Ok(());
+            }
+            for entry in fs::read_dir(source)? {
+                self.walk(&entry?.path(), base, dest_base, false, visited, stats, dirs)?;
+            }
+            dirs.push((source.to_path_buf(), target));
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let result = self.copy_file(source, &target, stats);
+            self.guard(source, stats, result)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Driver for BasicDriver {
+    fn copy_single(&self, source: &Path, dest: &Path, stats: StatSender) -> Result<()> {
+        if self.opts.symlink_policy() == SymlinkPolicy::NoDereference
+            && source.symlink_metadata()?.file_type().is_symlink()
+        {
+            return symlinks::recreate(source, dest);
+        }
+        let result = self.copy_file(source, dest, &stats);
+        self.guard(source, &stats, result)
+    }
+
+    fn copy_all(&self, sources: Vec<PathBuf>, dest: &Path, stats: StatSender) -> Result<()> {
+        for source in sources {
+            // An existing `dest` is a directory to copy into; a missing
+            // one names the copy of `source` itself.
+            let dest_exists = dest.exists();
+            let source_is_dir = match self.opts.symlink_policy() {
+                SymlinkPolicy::NoDereference => source.symlink_metadata().map_or(false, |m| m.is_dir()),
+                _ => source.is_dir(),
+            };
+            if !dest_exists && !source_is_dir {
+                self.copy_single(&source, dest, stats.clone())?;
+                continue;
+            }
+            let base = if dest_exists {
+                source.parent().unwrap_or(&source).to_path_buf()
+            } else {
+                source.clone()
+            };
+            let mut dirs = Vec::new();
+            let mut visited = symlinks::VisitedDirs::new();
+
+            let result = self.walk(&source, &base, dest, true, &mut visited, &stats, &mut dirs);
+            self.guard(&source, &stats, result)?;
+
+            // Directory attributes (in particular mtime) are applied
+            // only once all their children have been written, so that
+            // populating the directory doesn't bump its timestamp
+            // back to "now" after we've just set it.
+            for (source_dir, target_dir) in dirs {
+                let result = attrs::apply(&source_dir, &target_dir, self.opts.preserve(), &stats);
+                self.guard(&source_dir, &stats, result)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+mod archive;
+mod attrs;
+mod basic;
+mod reflink;
+mod sparse;
+mod symlinks;
+
+use std::path::{Path, PathBuf};
+
+use crate::errors::Result;
+use crate::operations::StatSender;
+use crate::options::Opts;
+
+/// A copy strategy. `load_driver` selects the implementation to use
+/// based on `Opts`; callers in `main` only ever see this trait.
+pub trait Driver {
+    /// Copy a single source file to `dest`.
+    fn copy_single(&self, source: &Path, dest: &Path, stats: StatSender) -> Result<()>;
+
+    /// Copy all `sources` into the `dest` directory (or, where `dest`
+    /// does not exist, to `dest` itself), recursing when
+    /// `--recursive` is set.
+    fn copy_all(&self, sources: Vec<PathBuf>, dest: &Path, stats: StatSender) -> Result<()>;
+}
+
+pub fn load_driver(opts: &Opts) -> Result<Box<dyn Driver>> {
+    if let Some(format) = opts.archive_format()? {
+        return Ok(Box::new(archive::ArchiveDriver::new(opts, format)));
+    }
+    Ok(Box::new(basic::BasicDriver::new(opts)))
+}
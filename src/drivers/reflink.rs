@@ -0,0 +1,67 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use crate::errors::{Result, XcpError};
+use crate::options::ReflinkMode;
+
+// linux/fs.h: #define FICLONE _IOW(0x94, 9, int)
+const FICLONE: libc::c_ulong = 0x4004_9409;
+
+/// Attempt to make `dest` share `source`'s extents via the
+/// filesystem's copy-on-write support (btrfs/XFS/bcachefs). Returns
+/// `Ok(true)` if the clone succeeded (the whole file is now
+/// "copied"), `Ok(false)` if reflinking isn't supported here and the
+/// caller should fall back to a normal copy.
+pub fn try_reflink(source: &Path, dest: &Path, mode: ReflinkMode) -> Result<bool> {
+    if mode == ReflinkMode::Never {
+        return Ok(false);
+    }
+
+    let src_file = File::open(source)?;
+    let dest_file = File::create(dest)?;
+
+    let ret = unsafe { libc::ioctl(dest_file.as_raw_fd(), FICLONE, src_file.as_raw_fd()) };
+    if ret == 0 {
+        return Ok(true);
+    }
+
+    let err = std::io::Error::last_os_error();
+    if mode == ReflinkMode::Always {
+        return Err(XcpError::InvalidDestination(
+            "Reflink requested with --reflink=always but the filesystem does not support it",
+        )
+        .into());
+    }
+
+    // ENOTSUP and EOPNOTSUPP are the same value on Linux, hence the
+    // `if` instead of an or-pattern (which would be a duplicate-match
+    // warning); EXDEV covers an attempted clone across filesystems.
+    // These are the only errors --reflink=auto should treat as "not
+    // supported here" and silently fall back from; anything else
+    // (e.g. a transient EIO) is a real error and must be surfaced
+    // rather than downgraded to a dense copy.
+    let errno = err.raw_os_error();
+    if errno == Some(libc::ENOTSUP) || errno == Some(libc::EOPNOTSUPP) || errno == Some(libc::EXDEV)
+    {
+        return Ok(false);
+    }
+
+    Err(err.into())
+}
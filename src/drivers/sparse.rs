@@ -0,0 +1,130 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use crate::errors::Result;
+use crate::operations::{StatSender, StatusUpdate};
+use crate::options::SparseMode;
+
+/// Whether `meta` looks like it could have holes: fewer 512-byte
+/// blocks allocated than its logical length implies.
+fn looks_sparse(meta: &Metadata) -> bool {
+    meta.blocks() * 512 < meta.len()
+}
+
+/// Copy `source` to `dest`, skipping holes when `mode` calls for it.
+/// Returns `Ok(true)` if the sparse extent walk was used (in which
+/// case the bytes actually written and the logical size have already
+/// been sent through `stats`), or `Ok(false)` if the caller should
+/// fall back to a dense copy.
+pub fn try_sparse_copy(
+    source: &Path,
+    dest: &Path,
+    mode: SparseMode,
+    stats: &StatSender,
+) -> Result<bool> {
+    let meta = source.metadata()?;
+
+    let probe = match mode {
+        SparseMode::Never => return Ok(false),
+        SparseMode::Always => true,
+        SparseMode::Auto => looks_sparse(&meta),
+    };
+    if !probe {
+        return Ok(false);
+    }
+
+    copy_sparse(source, dest, meta.len(), stats)?;
+    Ok(true)
+}
+
+fn copy_sparse(source: &Path, dest: &Path, len: u64, stats: &StatSender) -> io::Result<()> {
+    let mut src = File::open(source)?;
+    let dst = File::create(dest)?;
+    let fd = src.as_raw_fd();
+
+    let mut written = 0u64;
+    let mut pos: i64 = 0;
+    loop {
+        let data_start = match lseek(fd, pos, libc::SEEK_DATA)? {
+            Some(off) => off,
+            // No more data; the rest of the file (if any) is a hole.
+            None => break,
+        };
+        // SEEK_HOLE from a valid SEEK_DATA offset always succeeds (in
+        // the worst case by reporting EOF as a hole), so ENXIO here
+        // would be unexpected; if it happens anyway, everything up to
+        // `len` is copied as data. Other errors are propagated.
+        let hole_start = lseek(fd, data_start, libc::SEEK_HOLE)?.unwrap_or(len as i64);
+
+        src.seek(SeekFrom::Start(data_start as u64))?;
+        written += copy_range(&mut src, &dst, data_start as u64, hole_start as u64)?;
+
+        pos = hole_start;
+        if pos as u64 >= len {
+            break;
+        }
+    }
+
+    dst.set_len(len)?;
+
+    let send = |u| stats.send(u).map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+    send(StatusUpdate::Copied(written))?;
+    send(StatusUpdate::Size(len))?;
+
+    Ok(())
+}
+
+fn copy_range(src: &mut File, dst: &File, start: u64, end: u64) -> io::Result<u64> {
+    let mut dst = dst.try_clone()?;
+    dst.seek(SeekFrom::Start(start))?;
+
+    let mut remaining = end - start;
+    let mut buf = [0u8; 64 * 1024];
+    while remaining > 0 {
+        let chunk = remaining.min(buf.len() as u64) as usize;
+        let n = src.read(&mut buf[..chunk])?;
+        if n == 0 {
+            break;
+        }
+        dst.write_all(&buf[..n])?;
+        remaining -= n as u64;
+    }
+    Ok(end - start - remaining)
+}
+
+/// `lseek(fd, offset, whence)`. `ENXIO` (no more data/holes at or
+/// past `offset`) is the only expected failure and is translated into
+/// `Ok(None)`; anything else (e.g. `EINVAL` when the filesystem
+/// doesn't support hole-seeking, or a transient `EIO`) is a real
+/// error and must not be mistaken for "done" — doing so would leave
+/// the rest of the destination as an unintended, silent hole.
+fn lseek(fd: i32, offset: i64, whence: i32) -> io::Result<Option<i64>> {
+    let ret = unsafe { libc::lseek(fd, offset, whence) };
+    if ret == -1 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() == Some(libc::ENXIO) {
+            return Ok(None);
+        }
+        return Err(err);
+    }
+    Ok(Some(ret))
+}
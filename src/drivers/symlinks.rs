@@ -0,0 +1,51 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashSet;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use crate::errors::Result;
+
+/// Recreate the symlink at `source` at `dest`, rather than copying
+/// the file it points to.
+pub fn recreate(source: &Path, dest: &Path) -> Result<()> {
+    let target = std::fs::read_link(source)?;
+    if dest.symlink_metadata().is_ok() {
+        std::fs::remove_file(dest)?;
+    }
+    std::os::unix::fs::symlink(target, dest)?;
+    Ok(())
+}
+
+/// Tracks directories already descended into (by `(dev, inode)`) so
+/// that following symlinks with `-L` can't recurse forever through a
+/// link back into an ancestor directory.
+#[derive(Default)]
+pub struct VisitedDirs(HashSet<(u64, u64)>);
+
+impl VisitedDirs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `path` as visited, returning `false` if it had already
+    /// been seen (i.e. this would be a loop).
+    pub fn visit(&mut self, path: &Path) -> Result<bool> {
+        let meta = path.metadata()?;
+        Ok(self.0.insert((meta.dev(), meta.ino())))
+    }
+}
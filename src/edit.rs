@@ -0,0 +1,89 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+use tempfile::NamedTempFile;
+
+use crate::drivers::Driver;
+use crate::errors::{Result, XcpError};
+use crate::operations::StatSender;
+use crate::options::Opts;
+
+/// Run `--edit` mode: write `sources` to a temp file, let the user
+/// rearrange/rename them in `$EDITOR`, then copy each source to the
+/// destination path it ends up on.
+pub fn run(
+    opts: &Opts,
+    sources: Vec<PathBuf>,
+    driver: &dyn Driver,
+    stats: StatSender,
+) -> Result<()> {
+    let dests = edit_paths(&sources)?;
+
+    if dests.len() != sources.len() {
+        return Err(XcpError::InvalidArguments(
+            "files added or removed during editing".to_string(),
+        )
+        .into());
+    }
+
+    let mut seen = HashSet::new();
+    for dest in &dests {
+        if !seen.insert(dest) {
+            return Err(XcpError::InvalidArguments(format!(
+                "duplicate destination: {:?}",
+                dest
+            ))
+            .into());
+        }
+    }
+
+    for (source, dest) in sources.into_iter().zip(dests) {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        if opts.recursive && source.is_dir() {
+            driver.copy_all(vec![source], &dest, stats.clone())?;
+        } else {
+            driver.copy_single(&source, &dest, stats.clone())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn edit_paths(sources: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut tmp = NamedTempFile::new()?;
+    for source in sources {
+        writeln!(tmp, "{}", source.display())?;
+    }
+    tmp.flush()?;
+
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+    let status = Command::new(&editor).arg(tmp.path()).status()?;
+    if !status.success() {
+        return Err(XcpError::InvalidArguments(format!("{} exited with an error", editor)).into());
+    }
+
+    let contents = fs::read_to_string(tmp.path())?;
+    Ok(contents.lines().map(PathBuf::from).collect())
+}
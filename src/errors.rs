@@ -0,0 +1,52 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io;
+use std::path::PathBuf;
+
+use failure::Fail;
+
+pub type Result<T> = std::result::Result<T, failure::Error>;
+
+#[derive(Debug, Fail)]
+pub enum XcpError {
+    #[fail(display = "Invalid arguments: {}", _0)]
+    InvalidArguments(String),
+
+    #[fail(display = "Invalid source: {}", _0)]
+    InvalidSource(&'static str),
+
+    #[fail(display = "Invalid destination: {}", _0)]
+    InvalidDestination(&'static str),
+
+    #[fail(display = "{}: {:?}", _0, _1)]
+    DestinationExists(&'static str, PathBuf),
+
+    #[fail(display = "I/O error: {}", _0)]
+    IoError(#[cause] io::Error),
+
+    /// A failure surfaced through `--continue`'s per-file error
+    /// handling, where the original error type isn't otherwise known
+    /// to the collector.
+    #[fail(display = "{}", _0)]
+    Other(String),
+}
+
+impl From<io::Error> for XcpError {
+    fn from(error: io::Error) -> Self {
+        XcpError::IoError(error)
+    }
+}
@@ -15,6 +15,7 @@
  */
 
 mod drivers;
+mod edit;
 mod errors;
 mod operations;
 mod options;
@@ -22,11 +23,12 @@ mod progress;
 mod utils;
 
 use std::path::PathBuf;
+use std::process::exit;
 use std::sync::Arc;
 
 use crossbeam_channel as cbc;
 use libfs::is_same_file;
-use log::{error, info};
+use log::{error, info, warn};
 use operations::{StatSender, StatusUpdate};
 use options::Opts;
 use simplelog::{ColorChoice, Config, LevelFilter, SimpleLogger, TermLogger, TerminalMode};
@@ -34,6 +36,57 @@ use simplelog::{ColorChoice, Config, LevelFilter, SimpleLogger, TermLogger, Term
 use crate::drivers::load_driver;
 use crate::errors::{Result, XcpError};
 
+/// Drain `stat_rx`, updating the progress bar as we go. Without
+/// `--continue` the first error aborts immediately, matching prior
+/// behaviour. With it, errors are logged and counted but don't stop
+/// the remaining files from copying; a summary is printed at the end
+/// and the process exits with a distinct status if anything failed.
+fn collect_stats(
+    opts: &Opts,
+    pb: &dyn progress::ProgressBar,
+    stat_rx: cbc::Receiver<StatusUpdate>,
+) -> Result<()> {
+    let mut copied = 0u64;
+    let mut failures = Vec::new();
+
+    for stat in stat_rx {
+        match stat {
+            StatusUpdate::Copied(v) => {
+                copied += 1;
+                pb.inc(v);
+            }
+            StatusUpdate::Size(v) => pb.inc_size(v),
+            StatusUpdate::Error(path, e) => {
+                error!("Received error on {:?}: {}", path, e);
+                if !opts.continue_on_error {
+                    return Err(e.into());
+                }
+                failures.push((path, e));
+            }
+            // The data copy already succeeded; a failed attribute
+            // restore is always advisory and never aborts the copy,
+            // regardless of --continue.
+            StatusUpdate::Warning(path, e) => {
+                warn!("Failed to restore attribute on {:?}: {}", path, e);
+            }
+        }
+    }
+
+    pb.end();
+
+    if opts.continue_on_error {
+        println!("{} file(s) copied, {} failed", copied, failures.len());
+        for (path, e) in &failures {
+            error!("{:?}: {}", path, e);
+        }
+        if !failures.is_empty() {
+            exit(2);
+        }
+    }
+
+    Ok(())
+}
+
 fn init_logging(opts: &Opts) -> Result<()> {
     let log_level = match opts.verbose {
         0 => LevelFilter::Warn,
@@ -56,6 +109,25 @@ fn main() -> Result<()> {
     let opts = Arc::new(options::parse_args()?);
     init_logging(&opts)?;
 
+    if opts.edit {
+        let sources = options::expand_sources(&opts.paths, &opts)?;
+        if sources.is_empty() {
+            return Err(XcpError::InvalidSource("No source files found.").into());
+        }
+
+        let pb = progress::create_bar(&opts, 0)?;
+        let (stat_tx, stat_rx) = cbc::unbounded();
+        let stats = StatSender::new(stat_tx, &opts);
+        let driver = load_driver(&opts)?;
+
+        edit::run(&opts, sources, driver.as_ref(), stats)?;
+
+        collect_stats(&opts, pb.as_ref(), stat_rx)?;
+        info!("Copy complete");
+
+        return Ok(());
+    }
+
     let (dest, source_patterns) = opts
         .paths
         .split_last()
@@ -140,20 +212,9 @@ fn main() -> Result<()> {
 
     // Gather the results as we go; our end of the channel has been
     // moved to the driver call and will end when drained.
-    for stat in stat_rx {
-        match stat {
-            StatusUpdate::Copied(v) => pb.inc(v),
-            StatusUpdate::Size(v) => pb.inc_size(v),
-            StatusUpdate::Error(e) => {
-                // FIXME: Optional continue?
-                error!("Received error: {}", e);
-                return Err(e.into());
-            }
-        }
-    }
+    collect_stats(&opts, pb.as_ref(), stat_rx)?;
 
     info!("Copy complete");
-    pb.end();
 
     Ok(())
 }
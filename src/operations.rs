@@ -0,0 +1,61 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::path::PathBuf;
+
+use crossbeam_channel as cbc;
+
+use crate::errors::XcpError;
+use crate::options::Opts;
+
+/// Updates sent from a driver back to the collector loop in `main`.
+#[derive(Debug)]
+pub enum StatusUpdate {
+    /// Bytes actually transferred (advances the progress bar).
+    Copied(u64),
+    /// Bytes added to the logical total (used when the real transfer
+    /// size isn't known up-front).
+    Size(u64),
+    /// An error encountered while processing the given path. With
+    /// `--continue`, the collector logs this and keeps going instead
+    /// of aborting; without it, this is fatal.
+    Error(PathBuf, XcpError),
+    /// A per-attribute restoration failure (permissions, ownership,
+    /// timestamps, xattrs) on an otherwise successfully-copied path.
+    /// The data copy itself succeeded, so this is always advisory:
+    /// the collector logs it and continues regardless of
+    /// `--continue`.
+    Warning(PathBuf, XcpError),
+}
+
+/// A cheaply-cloneable handle drivers use to report progress and
+/// errors back to `main` without needing to know about `Opts`
+/// directly.
+#[derive(Clone)]
+pub struct StatSender {
+    tx: cbc::Sender<StatusUpdate>,
+}
+
+impl StatSender {
+    pub fn new(tx: cbc::Sender<StatusUpdate>, _opts: &Opts) -> Self {
+        Self { tx }
+    }
+
+    pub fn send(&self, update: StatusUpdate) -> crate::errors::Result<()> {
+        self.tx.send(update)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,399 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use glob::glob;
+use structopt::StructOpt;
+
+use crate::errors::{Result, XcpError};
+
+/// Control for the `--backup[=CONTROL]` option, modeled on GNU
+/// `cp`/`install`. Determines how an existing destination file is
+/// renamed out of the way before it is overwritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupMode {
+    /// Never make backups, even if `--backup` is given elsewhere.
+    None,
+    /// Always make simple backups, e.g. `dest~`.
+    Simple,
+    /// Make numbered backups, e.g. `dest.~1~`, `dest.~2~`...
+    Numbered,
+    /// Numbered if numbered backups exist, otherwise simple.
+    Existing,
+}
+
+impl FromStr for BackupMode {
+    type Err = XcpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" | "off" => Ok(BackupMode::None),
+            "simple" | "never" => Ok(BackupMode::Simple),
+            "numbered" | "t" => Ok(BackupMode::Numbered),
+            "existing" | "nil" => Ok(BackupMode::Existing),
+            _ => Err(XcpError::InvalidArguments(format!(
+                "Unknown backup control: {}",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(name = "xcp", about = "Copy SOURCE to DEST.")]
+pub struct Opts {
+    /// Do not overwrite an existing file.
+    #[structopt(short = "n", long = "no-clobber")]
+    pub no_clobber: bool,
+
+    /// Copy directories recursively.
+    #[structopt(short = "r", long = "recursive")]
+    pub recursive: bool,
+
+    /// Verbosity; can be repeated for more detail.
+    #[structopt(short = "v", long = "verbose", parse(from_occurrences))]
+    pub verbose: u8,
+
+    /// Make a backup of each existing destination file before it is
+    /// overwritten. The CONTROL argument, if given, selects the
+    /// backup method: `none`, `simple`, `numbered`, or `existing`
+    /// (numbered if numbered backups already exist, else simple).
+    /// With no CONTROL, `existing` is used.
+    #[structopt(
+        long = "backup",
+        name = "CONTROL",
+        help = "Make backups before overwriting destination files"
+    )]
+    backup: Option<Option<String>>,
+
+    /// Suffix to use for simple backups.
+    #[structopt(long = "suffix", default_value = "~")]
+    pub suffix: String,
+
+    /// Preserve the given attributes (mode, ownership, timestamps,
+    /// xattr) from source files; can be given as a comma-separated
+    /// list or repeated. Nothing is preserved unless named here.
+    #[structopt(
+        long = "preserve",
+        name = "ATTR",
+        use_delimiter = true,
+        possible_values = &["mode", "ownership", "timestamps", "xattr"]
+    )]
+    preserve_list: Vec<PreserveAttr>,
+
+    /// Do not preserve the given attributes, overriding any
+    /// `--preserve`.
+    #[structopt(
+        long = "no-preserve",
+        name = "NO_ATTR",
+        use_delimiter = true,
+        possible_values = &["mode", "ownership", "timestamps", "xattr"]
+    )]
+    no_preserve_list: Vec<PreserveAttr>,
+
+    /// Control use of copy-on-write clones on filesystems that
+    /// support them (btrfs, XFS, bcachefs). `auto` tries a reflink
+    /// and falls back to a normal copy if the filesystem doesn't
+    /// support it; `always` fails instead of falling back; `never`
+    /// skips reflinking entirely.
+    #[structopt(
+        long = "reflink",
+        name = "WHEN",
+        default_value = "auto",
+        possible_values = &["auto", "always", "never"]
+    )]
+    pub reflink: ReflinkMode,
+
+    /// Control detection and preservation of sparse files (files with
+    /// holes). `auto` probes the source's block count against its
+    /// length and only walks the extent map when that suggests
+    /// holes; `always` always walks it; `never` always copies dense.
+    #[structopt(
+        long = "sparse",
+        name = "SPARSE_WHEN",
+        default_value = "auto",
+        possible_values = &["auto", "always", "never"]
+    )]
+    pub sparse: SparseMode,
+
+    /// Never follow symbolic links; copy the link itself. The
+    /// default when `--recursive` is set.
+    #[structopt(short = "P", long = "no-dereference")]
+    pub no_dereference: bool,
+
+    /// Always follow symbolic links and copy what they point to.
+    #[structopt(short = "L", long = "dereference", conflicts_with = "no_dereference")]
+    pub dereference: bool,
+
+    /// Follow symbolic links named directly on the command line, but
+    /// not ones encountered while recursing.
+    #[structopt(short = "H", conflicts_with_all = &["no_dereference", "dereference"])]
+    pub command_line_dereference: bool,
+
+    /// Batch mode: open the resolved source paths in `$EDITOR` and
+    /// use the edited list as the destination for each, letting you
+    /// rename/reorganise many files at once. All PATHS are treated as
+    /// sources; there is no trailing destination argument.
+    #[structopt(long = "edit")]
+    pub edit: bool,
+
+    /// Instead of reproducing a directory tree at `dest`, stream all
+    /// recursively-discovered sources into a single compressed tar
+    /// archive written to `dest`.
+    #[structopt(
+        long = "archive",
+        name = "FORMAT",
+        possible_values = &["tar.xz", "tar.zst", "tar.gz"]
+    )]
+    archive: Option<String>,
+
+    /// Compression level for `--archive` (algorithm-specific range;
+    /// higher is smaller/slower).
+    #[structopt(long = "archive-level", default_value = "6")]
+    pub archive_level: u32,
+
+    /// xz dictionary/window size in MiB for `--archive=tar.xz`,
+    /// trading memory use for smaller output on highly-compressible
+    /// or highly-redundant data.
+    #[structopt(long = "archive-xz-dict-size")]
+    pub archive_xz_dict_size: Option<u32>,
+
+    /// Don't abort on the first error; log it, keep copying the
+    /// remaining files, and report a summary (and a distinct exit
+    /// code) at the end if anything failed.
+    #[structopt(short = "k", long = "continue", alias = "keep-going")]
+    pub continue_on_error: bool,
+
+    /// Source(s) and destination.
+    #[structopt(name = "PATHS")]
+    pub paths: Vec<String>,
+}
+
+impl Opts {
+    /// Resolve the `--backup` flag (and its optional CONTROL value)
+    /// into a concrete `BackupMode`. Absent entirely, this is
+    /// `BackupMode::None`.
+    pub fn backup_mode(&self) -> Result<BackupMode> {
+        match &self.backup {
+            None => Ok(BackupMode::None),
+            Some(None) => Ok(BackupMode::Existing),
+            Some(Some(control)) => BackupMode::from_str(control).map_err(|e| e.into()),
+        }
+    }
+
+    /// Resolve `--preserve`/`--no-preserve` into the concrete set of
+    /// attributes to restore after the data copy. Like GNU `cp`,
+    /// nothing is preserved unless asked for: each attribute starts
+    /// off and is only restored when named in `--preserve`.
+    pub fn preserve(&self) -> Preserve {
+        let mut preserve = Preserve::default();
+
+        for attr in &self.preserve_list {
+            preserve.set(*attr, true);
+        }
+        for attr in &self.no_preserve_list {
+            preserve.set(*attr, false);
+        }
+
+        preserve
+    }
+
+    /// Resolve `--archive`, if given, into a concrete format.
+    pub fn archive_format(&self) -> Result<Option<ArchiveFormat>> {
+        self.archive
+            .as_deref()
+            .map(ArchiveFormat::from_str)
+            .transpose()
+    }
+
+    /// Resolve `-P`/`-L`/`-H` into a single policy. With none given,
+    /// `cp`'s own default applies: don't dereference when recursing,
+    /// dereference otherwise.
+    pub fn symlink_policy(&self) -> SymlinkPolicy {
+        if self.dereference {
+            SymlinkPolicy::Dereference
+        } else if self.command_line_dereference {
+            SymlinkPolicy::CommandLineOnly
+        } else if self.no_dereference || self.recursive {
+            SymlinkPolicy::NoDereference
+        } else {
+            SymlinkPolicy::Dereference
+        }
+    }
+}
+
+/// How to treat symbolic links encountered while copying; see
+/// `-P`/`-L`/`-H`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Copy the link itself (`readlink` + `symlink`).
+    NoDereference,
+    /// Follow every link and copy its target.
+    Dereference,
+    /// Follow only links named on the command line.
+    CommandLineOnly,
+}
+
+/// Policy for `--reflink`: whether to attempt a copy-on-write clone
+/// instead of a byte-for-byte copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReflinkMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ReflinkMode {
+    type Err = XcpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ReflinkMode::Auto),
+            "always" => Ok(ReflinkMode::Always),
+            "never" => Ok(ReflinkMode::Never),
+            _ => Err(XcpError::InvalidArguments(format!(
+                "Unknown reflink mode: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Policy for `--sparse`: whether to probe for and preserve holes in
+/// a file rather than copying them as dense zeroed data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SparseMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for SparseMode {
+    type Err = XcpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(SparseMode::Auto),
+            "always" => Ok(SparseMode::Always),
+            "never" => Ok(SparseMode::Never),
+            _ => Err(XcpError::InvalidArguments(format!(
+                "Unknown sparse mode: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Output format for `--archive`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    TarXz,
+    TarZst,
+    TarGz,
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = XcpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tar.xz" => Ok(ArchiveFormat::TarXz),
+            "tar.zst" => Ok(ArchiveFormat::TarZst),
+            "tar.gz" => Ok(ArchiveFormat::TarGz),
+            _ => Err(XcpError::InvalidArguments(format!(
+                "Unknown archive format: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// One attribute class that can be carried over from source to
+/// destination; see `--preserve`/`--no-preserve`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreserveAttr {
+    Mode,
+    Ownership,
+    Timestamps,
+    Xattr,
+}
+
+impl FromStr for PreserveAttr {
+    type Err = XcpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mode" => Ok(PreserveAttr::Mode),
+            "ownership" => Ok(PreserveAttr::Ownership),
+            "timestamps" => Ok(PreserveAttr::Timestamps),
+            "xattr" => Ok(PreserveAttr::Xattr),
+            _ => Err(XcpError::InvalidArguments(format!(
+                "Unknown preserve attribute: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// The resolved set of attributes a driver should restore after
+/// copying a file's contents.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Preserve {
+    pub mode: bool,
+    pub ownership: bool,
+    pub timestamps: bool,
+    pub xattr: bool,
+}
+
+impl Preserve {
+    fn set(&mut self, attr: PreserveAttr, value: bool) {
+        match attr {
+            PreserveAttr::Mode => self.mode = value,
+            PreserveAttr::Ownership => self.ownership = value,
+            PreserveAttr::Timestamps => self.timestamps = value,
+            PreserveAttr::Xattr => self.xattr = value,
+        }
+    }
+}
+
+pub fn parse_args() -> Result<Opts> {
+    let opts = Opts::from_args();
+    Ok(opts)
+}
+
+/// Expand any glob patterns in the given source arguments into a
+/// concrete list of paths.
+pub fn expand_sources(source_patterns: &[String], _opts: &Opts) -> Result<Vec<PathBuf>> {
+    let mut sources = Vec::new();
+
+    for pattern in source_patterns {
+        let mut matched = false;
+        for entry in glob(pattern)? {
+            sources.push(entry?);
+            matched = true;
+        }
+        if !matched {
+            let path = PathBuf::from(pattern);
+            if path.exists() {
+                sources.push(path);
+            }
+        }
+    }
+
+    Ok(sources)
+}
@@ -0,0 +1,67 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use indicatif::{ProgressBar as IndicatifBar, ProgressStyle};
+
+use crate::errors::Result;
+use crate::options::Opts;
+
+/// A progress indicator for a running copy. `inc` tracks bytes
+/// actually transferred, `inc_size` tracks the logical total (used
+/// when the underlying copy is sparse or reflinked).
+pub trait ProgressBar {
+    fn inc_size(&self, size: u64);
+    fn inc(&self, size: u64);
+    fn end(&self);
+}
+
+struct Bar(IndicatifBar);
+
+impl ProgressBar for Bar {
+    fn inc_size(&self, size: u64) {
+        self.0.inc_length(size);
+    }
+
+    fn inc(&self, size: u64) {
+        self.0.inc(size);
+    }
+
+    fn end(&self) {
+        self.0.finish();
+    }
+}
+
+struct NoBar;
+
+impl ProgressBar for NoBar {
+    fn inc_size(&self, _size: u64) {}
+    fn inc(&self, _size: u64) {}
+    fn end(&self) {}
+}
+
+pub fn create_bar(opts: &Opts, size: u64) -> Result<Box<dyn ProgressBar>> {
+    if opts.verbose > 0 {
+        return Ok(Box::new(NoBar));
+    }
+
+    let bar = IndicatifBar::new(size);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("{bytes}/{total_bytes} [{bar:40}] {bytes_per_sec} eta: {eta}"),
+    );
+
+    Ok(Box::new(Bar(bar)))
+}
@@ -0,0 +1,89 @@
+/*
+ * Copyright © 2018, Steve Smith <tarkasteve@gmail.com>
+ *
+ * This program is free software: you can redistribute it and/or
+ * modify it under the terms of the GNU General Public License version
+ * 3 as published by the Free Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::path::{Path, PathBuf};
+
+use crate::errors::Result;
+use crate::options::BackupMode;
+
+/// Re-root `path` (found while walking under `base`) onto `new_base`,
+/// e.g. `(src/a/b, src, dest)` -> `dest/a/b`.
+pub fn target_path(path: &Path, base: &Path, new_base: &Path) -> Result<PathBuf> {
+    let suffix = path.strip_prefix(base)?;
+    Ok(new_base.join(suffix))
+}
+
+/// If `dest` exists and `mode` calls for it, compute the backup path
+/// it should be renamed to before being overwritten. Returns `None`
+/// when no backup is required.
+pub fn backup_path(dest: &Path, mode: BackupMode, suffix: &str) -> Result<Option<PathBuf>> {
+    if !dest.exists() || mode == BackupMode::None {
+        return Ok(None);
+    }
+
+    let numbered_exists = next_numbered_backup(dest)?.is_some();
+    let use_numbered = match mode {
+        BackupMode::Numbered => true,
+        BackupMode::Existing => numbered_exists,
+        BackupMode::Simple => false,
+        BackupMode::None => unreachable!(),
+    };
+
+    if use_numbered {
+        let n = next_numbered_backup(dest)?.unwrap_or(1);
+        let name = format!("{}.~{}~", dest.display(), n);
+        Ok(Some(PathBuf::from(name)))
+    } else {
+        let mut name = dest.as_os_str().to_os_string();
+        name.push(suffix);
+        Ok(Some(PathBuf::from(name)))
+    }
+}
+
+/// Scan `dest`'s parent directory for existing `dest.~N~` backups and
+/// return the next number to use, or `None` if none exist yet.
+fn next_numbered_backup(dest: &Path) -> Result<Option<u64>> {
+    // `dest.parent()` is `Some("")` for a bare relative path like
+    // `b`, not `None`, so the `unwrap_or` fallback alone would never
+    // trigger and the scan below would silently look in "" (always
+    // non-existent) instead of the current directory.
+    let parent = match dest.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let file_name = match dest.file_name() {
+        Some(n) => n.to_string_lossy().into_owned(),
+        None => return Ok(None),
+    };
+    let prefix = format!("{}.~", file_name);
+
+    let mut max = None;
+    if parent.is_dir() {
+        for entry in std::fs::read_dir(parent)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(rest) = name.strip_prefix(&prefix) {
+                if let Some(num) = rest.strip_suffix('~') {
+                    if let Ok(n) = num.parse::<u64>() {
+                        max = Some(max.map_or(n, |m: u64| m.max(n)));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(max.map(|m| m + 1))
+}
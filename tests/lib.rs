@@ -2,7 +2,9 @@ use failure::Error;
 
 use escargot::CargoBuild;
 use std::fs::{File, create_dir_all};
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::{symlink, MetadataExt};
+use std::path::Path;
 use std::process::Output;
 use tempfile::tempdir;
 
@@ -11,6 +13,16 @@ fn run(args: &[&str]) -> Result<Output, Error> {
     Ok(out)
 }
 
+fn run_in(dir: &Path, args: &[&str]) -> Result<Output, Error> {
+    let out = CargoBuild::new()
+        .run()?
+        .command()
+        .current_dir(dir)
+        .args(args)
+        .output()?;
+    Ok(out)
+}
+
 #[test]
 fn basic_help() -> Result<(), Error> {
     let out = run(&["--help"])?;
@@ -170,3 +182,132 @@ fn copy_all_dirs() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn backup_numbered_bare_relative_dest() -> Result<(), Error> {
+    // Regression test: a bare relative dest (no directory component)
+    // used to make the numbered-backup scan never run, so every
+    // --backup=numbered run started back at ~1~ and clobbered the
+    // previous backup instead of counting up.
+    let dir = tempdir()?;
+
+    {
+        let source = File::create(dir.path().join("source.txt"))?;
+        write!(&source, "original source")?;
+        let dest = File::create(dir.path().join("dest.txt"))?;
+        write!(&dest, "original dest")?;
+    }
+
+    let out = run_in(dir.path(), &["--backup=numbered", "source.txt", "dest.txt"])?;
+    assert!(out.status.success());
+    assert!(dir.path().join("dest.txt.~1~").exists());
+
+    {
+        let source = File::create(dir.path().join("source.txt"))?;
+        write!(&source, "second source")?;
+    }
+
+    let out = run_in(dir.path(), &["--backup=numbered", "source.txt", "dest.txt"])?;
+    assert!(out.status.success());
+    assert!(dir.path().join("dest.txt.~1~").exists());
+    assert!(dir.path().join("dest.txt.~2~").exists());
+
+    let mut backup1 = String::new();
+    File::open(dir.path().join("dest.txt.~1~"))?.read_to_string(&mut backup1)?;
+    assert!(backup1 == "original dest");
+
+    let mut backup2 = String::new();
+    File::open(dir.path().join("dest.txt.~2~"))?.read_to_string(&mut backup2)?;
+    assert!(backup2 == "original source");
+
+    Ok(())
+}
+
+#[test]
+fn sparse_copy_preserves_holes() -> Result<(), Error> {
+    let dir = tempdir()?;
+    let source_path = dir.path().join("source.img");
+    let dest_path = dir.path().join("dest.img");
+
+    let head = b"head of the file";
+    let tail = b"tail of the file";
+    let len = 8 * 1024 * 1024;
+
+    {
+        let mut source = File::create(&source_path)?;
+        source.write_all(head)?;
+        source.seek(SeekFrom::Start(len - tail.len() as u64))?;
+        source.write_all(tail)?;
+        source.set_len(len)?;
+    }
+
+    let out = run(&[
+        "--sparse=always",
+        source_path.to_str().unwrap(),
+        dest_path.to_str().unwrap(),
+    ])?;
+    assert!(out.status.success());
+
+    let dest_meta = dest_path.metadata()?;
+    assert!(dest_meta.len() == len);
+    // The copy must actually have used the sparse path rather than
+    // writing `len` bytes of real data for the hole in the middle.
+    assert!(dest_meta.blocks() * 512 < len);
+
+    let mut dest = File::open(&dest_path)?;
+    let mut buf = vec![0u8; head.len()];
+    dest.read_exact(&mut buf)?;
+    assert!(buf == head);
+
+    dest.seek(SeekFrom::Start(len - tail.len() as u64))?;
+    let mut buf = vec![0u8; tail.len()];
+    dest.read_exact(&mut buf)?;
+    assert!(buf == tail);
+
+    let mut hole = vec![0u8; 4096];
+    dest.seek(SeekFrom::Start(len / 2))?;
+    dest.read_exact(&mut hole)?;
+    assert!(hole.iter().all(|b| *b == 0));
+
+    Ok(())
+}
+
+#[test]
+fn continue_reports_summary_and_exits_nonzero() -> Result<(), Error> {
+    let dir = tempdir()?;
+
+    let good_path = dir.path().join("good.txt");
+    {
+        let good = File::create(&good_path)?;
+        write!(&good, "this one copies fine")?;
+    }
+
+    // A directory whose only entry is a dangling symlink; with -L the
+    // walk must dereference it, fail on that one file, and (with
+    // --continue) report the failure without aborting the other source.
+    let bad_dir = dir.path().join("baddir");
+    create_dir_all(&bad_dir)?;
+    symlink(dir.path().join("does-not-exist"), bad_dir.join("badlink"))?;
+
+    let dest_base = dir.path().join("dest");
+    create_dir_all(&dest_base)?;
+
+    let out = run(&[
+        "-r",
+        "-L",
+        "--continue",
+        good_path.to_str().unwrap(),
+        bad_dir.to_str().unwrap(),
+        dest_base.to_str().unwrap(),
+    ])?;
+
+    assert!(!out.status.success());
+    assert!(out.status.code().unwrap() == 2);
+
+    let stdout = String::from_utf8(out.stdout)?;
+    assert!(stdout.contains("1 file(s) copied, 1 failed"));
+
+    assert!(dest_base.join("good.txt").exists());
+
+    Ok(())
+}
+